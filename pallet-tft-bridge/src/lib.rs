@@ -0,0 +1,364 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+mod migration_burn_withdraw;
+pub mod types;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+    use super::types;
+    use frame_support::{
+        dispatch::DispatchResultWithPostInfo,
+        pallet_prelude::*,
+        traits::{Currency, Get},
+    };
+    use codec::Decode;
+    use frame_system::{ensure_signed, pallet_prelude::*};
+    use sp_runtime::{
+        traits::{Saturating, Zero},
+        SaturatedConversion,
+    };
+    use sp_std::prelude::*;
+
+    pub type BalanceOf<T> =
+        <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+        type Currency: Currency<Self::AccountId>;
+
+        /// Maximum number of storage entries the lazy burn -> withdraw
+        /// rename migration is allowed to move in a single block.
+        #[pallet::constant]
+        type MaxMigrationItemsPerBlock: Get<u32>;
+
+        /// Number of blocks a withdraw transaction may sit unexecuted
+        /// before it becomes eligible for a refund back to its `source`.
+        #[pallet::constant]
+        type RetryInterval: Get<BlockNumberFor<Self>>;
+
+        /// Maximum number of `WithdrawTransactions` entries the expiry
+        /// scan below is allowed to inspect in a single block.
+        #[pallet::constant]
+        type MaxExpiryChecksPerBlock: Get<u32>;
+
+        /// Number of validator signatures a withdraw transaction must
+        /// collect before `handle_withdraw`/`refund_withdraw_transaction`
+        /// will act on it.
+        #[pallet::constant]
+        type SignatureThreshold: Get<u32>;
+    }
+
+    #[pallet::pallet]
+    pub struct Pallet<T>(_);
+
+    #[pallet::storage]
+    #[pallet::getter(fn burn_transaction_id)]
+    pub type BurnTransactionID<T> = StorageValue<_, u64, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn burn_fee)]
+    pub type BurnFee<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn burn_transactions)]
+    pub type BurnTransactions<T: Config> =
+        StorageMap<_, Blake2_128Concat, u64, types::BurnTransaction<T::AccountId>, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn executed_burn_transaction)]
+    pub type ExecutedBurnTransactions<T: Config> =
+        StorageMap<_, Blake2_128Concat, u64, types::BurnTransaction<T::AccountId>, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn withdraw_transaction_id)]
+    pub type WithdrawTransactionID<T> = StorageValue<_, u64, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn withdraw_fee)]
+    pub type WithdrawFee<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn withdraw_transactions)]
+    pub type WithdrawTransactions<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        u64,
+        types::WithdrawTransaction<T::AccountId>,
+        OptionQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn executed_withdraw_transaction)]
+    pub type ExecutedWithdrawTransactions<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        u64,
+        types::ExecutedWithdrawTransaction<T::AccountId, BalanceOf<T>>,
+        OptionQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn pallet_version)]
+    pub type PalletVersion<T> = StorageValue<_, types::StorageVersion, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn refund_transactions)]
+    pub type RefundTransactions<T: Config> =
+        StorageMap<_, Blake2_128Concat, u64, types::RefundTransaction<T::AccountId>, OptionQuery>;
+
+    /// Ids of withdraw transactions for which `WithdrawTransactionExpired`
+    /// has already been emitted, so the `on_finalize` scan below doesn't
+    /// re-notify the same still-pending transaction every block.
+    #[pallet::storage]
+    #[pallet::getter(fn withdraw_expiry_notified)]
+    pub type WithdrawExpiryNotified<T> = StorageMap<_, Blake2_128Concat, u64, (), OptionQuery>;
+
+    /// Last `WithdrawTransactions` key inspected by the expiry scan,
+    /// resumed from on the next block so the scan stays bounded by
+    /// `T::MaxExpiryChecksPerBlock` instead of visiting the whole map.
+    #[pallet::storage]
+    #[pallet::getter(fn withdraw_expiry_scan_cursor)]
+    pub type WithdrawExpiryScanCursor<T> = StorageValue<_, Option<u64>, ValueQuery>;
+
+    #[pallet::event]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        WithdrawTransactionCreated(u64, Option<Vec<u8>>, Vec<u8>, BalanceOf<T>),
+        WithdrawTransactionExecuted(u64, T::AccountId, BalanceOf<T>),
+        WithdrawTransactionExpired(u64),
+        RefundTransactionCompleted(u64, T::AccountId, BalanceOf<T>),
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        TransactionNotFound,
+        WithdrawTransactionAlreadyExecuted,
+        WithdrawTransactionHasNoSource,
+        NotEnoughSignatures,
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(_n: BlockNumberFor<T>) -> Weight {
+            migration_burn_withdraw::rename_burn_to_withdraw_step::<T>()
+        }
+
+        fn on_finalize(n: BlockNumberFor<T>) {
+            // Surface withdraws that have sat unexecuted past
+            // `RetryInterval` so the bridge daemon knows they are now
+            // eligible for `refund_withdraw_transaction`. `on_finalize` is
+            // mandatory and unweighted, so this only ever inspects up to
+            // `MaxExpiryChecksPerBlock` entries, resuming from the saved
+            // cursor, and never re-notifies an id it has already flagged.
+            let max_checks = T::MaxExpiryChecksPerBlock::get() as usize;
+            let start_key = WithdrawExpiryScanCursor::<T>::get()
+                .map(WithdrawTransactions::<T>::hashed_key_for);
+            let iter = match start_key {
+                Some(key) => WithdrawTransactions::<T>::iter_from(key),
+                None => WithdrawTransactions::<T>::iter(),
+            };
+
+            let mut checked = 0usize;
+            let mut last_processed = None;
+            let mut reached_end = true;
+            for (id, tx) in iter {
+                if checked >= max_checks {
+                    // Leave this entry unprocessed for next block; don't
+                    // advance the cursor onto it.
+                    reached_end = false;
+                    break;
+                }
+                checked += 1;
+                last_processed = Some(id);
+
+                if WithdrawExpiryNotified::<T>::contains_key(id) {
+                    continue;
+                }
+                let created_at: BlockNumberFor<T> = tx.block.saturated_into();
+                if n.saturating_sub(created_at) >= T::RetryInterval::get() {
+                    WithdrawExpiryNotified::<T>::insert(id, ());
+                    Self::deposit_event(Event::WithdrawTransactionExpired(id));
+                }
+            }
+
+            // Resume strictly after the last entry actually processed
+            // (matching `iter_from`'s "after this key" semantics), or wrap
+            // back to the start once the scan reaches the end of the map.
+            WithdrawExpiryScanCursor::<T>::put(if reached_end { None } else { last_processed });
+        }
+    }
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Propose a withdraw transaction, or add the caller's signature to
+        /// an existing proposal for the same sequence number.
+        #[pallet::call_index(0)]
+        #[pallet::weight(10_000)]
+        pub fn propose_withdraw_transaction(
+            origin: OriginFor<T>,
+            sequence_number: u64,
+            source: Option<Vec<u8>>,
+            target: Vec<u8>,
+            amount: u64,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                !MigrationInProgress::<T>::get(),
+                Error::<T>::TransactionNotFound
+            );
+
+            match WithdrawTransactions::<T>::get(sequence_number) {
+                Some(mut tx) => {
+                    if !tx.signatures.contains(&who) {
+                        tx.signatures.push(who);
+                    }
+                    WithdrawTransactions::<T>::insert(sequence_number, tx);
+                }
+                None => {
+                    let tx = types::WithdrawTransaction {
+                        block: frame_system::Pallet::<T>::block_number().saturated_into(),
+                        amount,
+                        source: source.clone(),
+                        target: target.clone(),
+                        signatures: sp_std::vec![who],
+                        sequence_number,
+                    };
+                    WithdrawTransactions::<T>::insert(sequence_number, tx);
+                }
+            }
+
+            Self::deposit_event(Event::WithdrawTransactionCreated(
+                sequence_number,
+                source,
+                target,
+                Zero::zero(),
+            ));
+            Ok(().into())
+        }
+
+        /// Execute a withdraw transaction that has collected at least
+        /// `T::SignatureThreshold` validator signatures: move it from the
+        /// pending map into `ExecutedWithdrawTransactions`, carrying its
+        /// `source` along so the bridge daemon can still attribute it.
+        #[pallet::call_index(1)]
+        #[pallet::weight(10_000)]
+        pub fn handle_withdraw(
+            origin: OriginFor<T>,
+            sequence_number: u64,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                !MigrationInProgress::<T>::get(),
+                Error::<T>::TransactionNotFound
+            );
+            ensure!(
+                !ExecutedWithdrawTransactions::<T>::contains_key(sequence_number),
+                Error::<T>::WithdrawTransactionAlreadyExecuted
+            );
+
+            let tx = WithdrawTransactions::<T>::get(sequence_number)
+                .ok_or(Error::<T>::TransactionNotFound)?;
+            ensure!(
+                tx.signatures.len() as u32 >= T::SignatureThreshold::get(),
+                Error::<T>::NotEnoughSignatures
+            );
+
+            // Record the `WithdrawFee` in force at execution time on the
+            // executed transaction itself, so reconciliation later doesn't
+            // have to guess which fee schedule applied.
+            let fee = WithdrawFee::<T>::get();
+
+            let executed = types::ExecutedWithdrawTransaction {
+                block: frame_system::Pallet::<T>::block_number().saturated_into(),
+                amount: tx.amount,
+                source: tx.source,
+                target: tx.target,
+                tx_id: sequence_number,
+                signatures: tx.signatures,
+                fee,
+            };
+            ExecutedWithdrawTransactions::<T>::insert(sequence_number, executed);
+            WithdrawTransactions::<T>::remove(sequence_number);
+            WithdrawExpiryNotified::<T>::remove(sequence_number);
+
+            Self::deposit_event(Event::WithdrawTransactionExecuted(sequence_number, who, fee));
+            Ok(().into())
+        }
+
+        /// Refund a withdraw transaction whose target the bridge cannot
+        /// deliver to: gated behind the same `T::SignatureThreshold` as
+        /// `handle_withdraw`, it returns the locked amount to the transaction's
+        /// `source` account and moves the record into `RefundTransactions`.
+        #[pallet::call_index(2)]
+        #[pallet::weight(10_000)]
+        pub fn refund_withdraw_transaction(
+            origin: OriginFor<T>,
+            sequence_number: u64,
+        ) -> DispatchResultWithPostInfo {
+            ensure_signed(origin)?;
+            ensure!(
+                !MigrationInProgress::<T>::get(),
+                Error::<T>::TransactionNotFound
+            );
+            ensure!(
+                !ExecutedWithdrawTransactions::<T>::contains_key(sequence_number),
+                Error::<T>::WithdrawTransactionAlreadyExecuted
+            );
+
+            let tx = WithdrawTransactions::<T>::get(sequence_number)
+                .ok_or(Error::<T>::TransactionNotFound)?;
+            ensure!(
+                tx.signatures.len() as u32 >= T::SignatureThreshold::get(),
+                Error::<T>::NotEnoughSignatures
+            );
+            let source = tx
+                .source
+                .clone()
+                .ok_or(Error::<T>::WithdrawTransactionHasNoSource)?;
+            let target_account = T::AccountId::decode(&mut source.as_slice())
+                .map_err(|_| Error::<T>::WithdrawTransactionHasNoSource)?;
+
+            let amount: BalanceOf<T> = tx.amount.saturated_into();
+            T::Currency::deposit_creating(&target_account, amount);
+
+            let refund = types::RefundTransaction {
+                block: frame_system::Pallet::<T>::block_number().saturated_into(),
+                amount: tx.amount,
+                source: tx.source,
+                target: tx.target,
+                tx_id: sequence_number,
+                signatures: tx.signatures,
+            };
+            RefundTransactions::<T>::insert(sequence_number, refund);
+            WithdrawTransactions::<T>::remove(sequence_number);
+            WithdrawExpiryNotified::<T>::remove(sequence_number);
+
+            Self::deposit_event(Event::RefundTransactionCompleted(
+                sequence_number,
+                target_account,
+                amount,
+            ));
+            Ok(().into())
+        }
+    }
+
+    #[pallet::storage]
+    #[pallet::getter(fn migration_in_progress)]
+    pub type MigrationInProgress<T> = StorageValue<_, bool, ValueQuery>;
+
+    /// Stage/cursor pair driving the lazy burn -> withdraw rename
+    /// migration across multiple blocks. It records no storage version:
+    /// the final step reads `PalletVersion` itself. `None` once the
+    /// migration has fully drained and `PalletVersion` has been bumped.
+    #[pallet::storage]
+    #[pallet::getter(fn migration_state)]
+    pub type MigrationState<T> = StorageValue<_, Option<types::MigrationState>, ValueQuery>;
+}
@@ -1,11 +1,16 @@
+use crate::types::{MigrationStage, MigrationState as MigrationStateInner};
 use crate::Config;
 use crate::*;
+use codec::{Decode, Encode};
 use frame_support::{
-    migration::move_prefix, storage::storage_prefix, traits::Get, traits::OnRuntimeUpgrade,
+    storage::{storage_prefix, unhashed},
+    traits::{Get, OnRuntimeUpgrade},
     weights::Weight,
 };
 use log::info;
+use sp_io::storage as raw_storage;
 use sp_std::marker::PhantomData;
+use sp_std::prelude::*;
 
 #[cfg(feature = "try-runtime")]
 use frame_support::traits::OnRuntimeUpgradeHelpersExt;
@@ -17,6 +22,14 @@ pub struct RenameBurnToWithdraw<T: Config>(PhantomData<T>);
 impl<T: Config> OnRuntimeUpgrade for RenameBurnToWithdraw<T> {
     #[cfg(feature = "try-runtime")]
     fn pre_upgrade() -> Result<(), &'static str> {
+        // This migration must not have already run. Assert on the on-chain
+        // version rather than on burn storage being populated, since a
+        // chain with no outstanding burns is a valid pre-migration state.
+        assert!(
+            PalletVersion::<T>::get() < types::StorageVersion::V2,
+            "RenameBurnToWithdraw must only run below storage version V2"
+        );
+
         // Store number of transactions in temp storage
         let tx_count: u64 = BurnTransactions::<T>::iter_keys().count().saturated_into();
         let executed_tx_count: u64 = ExecutedBurnTransactions::<T>::iter_keys()
@@ -55,63 +68,358 @@ impl<T: Config> OnRuntimeUpgrade for RenameBurnToWithdraw<T> {
     }
 
     fn on_runtime_upgrade() -> Weight {
-        rename_burn_to_withdraw::<T>()
+        // Guard against this migration being left in the `Executive`
+        // tuple across releases: re-arming it once the chain is already
+        // on V2 would reset `WithdrawTransactionID`/`WithdrawFee` back to
+        // the (by then stale) burn values and corrupt state.
+        if PalletVersion::<T>::get() >= types::StorageVersion::V2 {
+            info!(" >>> RenameBurnToWithdraw already applied, skipping");
+            return T::DbWeight::get().reads(1);
+        }
+
+        start_rename_burn_to_withdraw::<T>()
     }
 
     #[cfg(feature = "try-runtime")]
     fn post_upgrade() -> Result<(), &'static str> {
-        assert!(PalletVersion::<T>::get() >= types::StorageVersion::V2);
+        // The rename no longer completes within this call: it only arms
+        // the lazy migration driven from `on_initialize`. Check that it
+        // was armed correctly instead of asserting on final storage.
+        assert!(
+            MigrationState::<T>::get().is_some(),
+            "lazy rename migration was not armed"
+        );
+        assert!(
+            MigrationInProgress::<T>::get(),
+            "MigrationInProgress flag was not set"
+        );
 
-        let pre_tx_count = Self::get_temp_storage("pre_tx_count").unwrap_or(0u64);
-        let pre_executed_tx_count = Self::get_temp_storage("pre_executed_tx_count").unwrap_or(0u64);
-        let pre_tx_id = Self::get_temp_storage("pre_tx_id").unwrap_or(0u64);
-        let pre_tx_fee = Self::get_temp_storage("pre_tx_fee").unwrap_or(0u64);
+        info!("👥  TFChain TFT Bridge pallet RenameBurnToWithdraw armed, draining lazily ✅",);
 
-        let post_tx_count: u64 = WithdrawTransactions::<T>::iter_keys()
+        Ok(())
+    }
+}
+
+/// Arms the lazy burn -> withdraw rename migration: copies the scalar
+/// id/fee values immediately (cheap, O(1)) and schedules the two
+/// transaction maps to be drained a bounded number of entries at a time
+/// from `on_initialize`, so a chain with a large backlog of burn records
+/// never exceeds the block weight budget in one go.
+pub fn start_rename_burn_to_withdraw<T: Config>() -> Weight {
+    info!(" >>> Arming lazy burn -> withdraw rename migration...");
+
+    // Copy withdraw values from burn values
+    WithdrawTransactionID::<T>::set(Pallet::<T>::burn_transaction_id());
+    WithdrawFee::<T>::set(Pallet::<T>::burn_fee());
+
+    // Reset burn values
+    BurnTransactionID::<T>::set(0);
+    BurnFee::<T>::set(0);
+
+    MigrationState::<T>::set(Some(MigrationStateInner {
+        stage: MigrationStage::BurnTransactions,
+        cursor: None,
+    }));
+    MigrationInProgress::<T>::set(true);
+
+    info!(" <<< Lazy rename migration armed, will drain from on_initialize");
+
+    T::DbWeight::get().reads_writes(4, 6)
+}
+
+/// Moves at most `T::MaxMigrationItemsPerBlock` entries from the old
+/// burn storage maps into their withdraw equivalents, resuming from the
+/// saved cursor. Called unconditionally from `on_initialize`; a no-op
+/// (single read) whenever no migration is in progress.
+pub fn rename_burn_to_withdraw_step<T: Config>() -> Weight {
+    let mut state = match MigrationState::<T>::get() {
+        Some(state) => state,
+        None => return T::DbWeight::get().reads(1),
+    };
+
+    let max_items = T::MaxMigrationItemsPerBlock::get() as u64;
+    let mut moved = 0u64;
+
+    if state.stage == MigrationStage::BurnTransactions {
+        // `WithdrawTransaction` already carries the `source` field (it's
+        // part of the compiled struct regardless of on-chain version), so
+        // a raw byte copy from `BurnTransaction` would shift every field
+        // from `target` onwards. Decode/re-encode with `source: None`
+        // instead, same as the executed stage below.
+        let old_prefix = storage_prefix(b"TFTBridgeModule", b"BurnTransactions");
+        let new_prefix = storage_prefix(b"TFTBridgeModule", b"WithdrawTransactions");
+        moved += drain_transform_step(
+            &old_prefix,
+            &new_prefix,
+            &mut state.cursor,
+            max_items,
+            |old: types::BurnTransaction<T::AccountId>| types::WithdrawTransaction {
+                block: old.block,
+                amount: old.amount,
+                source: None,
+                target: old.target,
+                signatures: old.signatures,
+                sequence_number: old.sequence_number,
+            },
+        );
+
+        if state.cursor.is_none() {
+            // Exhausted this map; move on to the executed map, spending
+            // any leftover budget in this same block.
+            state.stage = MigrationStage::ExecutedBurnTransactions;
+        }
+    }
+
+    if state.stage == MigrationStage::ExecutedBurnTransactions && moved < max_items {
+        // `ExecutedWithdrawTransaction` reorders and adds fields relative
+        // to `BurnTransaction` (`tx_id`/`signatures` are swapped, plus
+        // `source`/`fee`), so this stage cannot use a raw byte copy: it
+        // must decode the old shape and re-encode the new one per entry.
+        let old_prefix = storage_prefix(b"TFTBridgeModule", b"ExecutedBurnTransactions");
+        let new_prefix = storage_prefix(b"TFTBridgeModule", b"ExecutedWithdrawTransactions");
+        let remaining = max_items - moved;
+        moved += drain_transform_step(
+            &old_prefix,
+            &new_prefix,
+            &mut state.cursor,
+            remaining,
+            |old: types::BurnTransaction<T::AccountId>| types::ExecutedWithdrawTransaction {
+                block: old.block,
+                amount: old.amount,
+                source: None,
+                target: old.target,
+                tx_id: old.sequence_number,
+                signatures: old.signatures,
+                fee: Default::default(),
+            },
+        );
+
+        if state.cursor.is_none() {
+            // Both maps drained: finalize the migration. Only raise
+            // `PalletVersion` to V2, never lower it, so that a later
+            // version already on chain is left in place and the later
+            // migrations' idempotency guards keep holding.
+            if PalletVersion::<T>::get() < types::StorageVersion::V2 {
+                PalletVersion::<T>::set(types::StorageVersion::V2);
+            }
+            MigrationState::<T>::kill();
+            MigrationInProgress::<T>::set(false);
+            info!(" <<< Lazy rename migration complete, storage version bumped");
+            return T::DbWeight::get().reads_writes(moved + 2, moved + 2);
+        }
+    }
+
+    MigrationState::<T>::set(Some(state));
+    T::DbWeight::get().reads_writes(max_items, max_items)
+}
+
+/// Decodes each raw value under `old_prefix` as `Old`, re-encodes it via
+/// `transform`, and stores the result under the matching suffix of
+/// `new_prefix`, resuming after `*cursor` and removing the source key as
+/// each entry is moved. Sets `*cursor` to `None` once `old_prefix` is
+/// exhausted. A value that fails to decode as `Old` is dropped along with
+/// its key, since there is no sound way to carry it forward.
+fn drain_transform_step<Old, New>(
+    old_prefix: &[u8],
+    new_prefix: &[u8],
+    cursor: &mut Option<Vec<u8>>,
+    limit: u64,
+    transform: impl Fn(Old) -> New,
+) -> u64
+where
+    Old: Decode,
+    New: Encode,
+{
+    let mut moved = 0u64;
+    let mut probe = cursor.clone().unwrap_or_else(|| old_prefix.to_vec());
+
+    loop {
+        let next = match raw_storage::next_key(&probe) {
+            Some(key) if key.starts_with(old_prefix) => key,
+            _ => {
+                *cursor = None;
+                return moved;
+            }
+        };
+
+        if moved >= limit {
+            *cursor = Some(probe);
+            return moved;
+        }
+
+        if let Some(value) = unhashed::get_raw(&next) {
+            if let Ok(old) = Old::decode(&mut value.as_slice()) {
+                let suffix = &next[old_prefix.len()..];
+                let mut new_key = new_prefix.to_vec();
+                new_key.extend_from_slice(suffix);
+                unhashed::put_raw(&new_key, &transform(old).encode());
+            }
+            unhashed::kill(&next);
+        }
+
+        moved += 1;
+        probe = next;
+    }
+}
+
+pub struct AddWithdrawTransactionSource<T: Config>(PhantomData<T>);
+
+impl<T: Config> OnRuntimeUpgrade for AddWithdrawTransactionSource<T> {
+    #[cfg(feature = "try-runtime")]
+    fn pre_upgrade() -> Result<(), &'static str> {
+        assert!(
+            PalletVersion::<T>::get() < types::StorageVersion::V3,
+            "AddWithdrawTransactionSource must only run below storage version V3"
+        );
+
+        let tx_count: u64 = WithdrawTransactions::<T>::iter_keys()
             .count()
             .saturated_into();
-        let post_executed_tx_count: u64 = ExecutedWithdrawTransactions::<T>::iter_keys()
+        Self::set_temp_storage(tx_count, "pre_withdraw_tx_count");
+
+        log::info!("🔎 AddWithdrawTransactionSource pre migration:");
+        log::info!(" --> withdraw tx count: {:?}", tx_count);
+        log::info!("👥  TFChain TFT Bridge pallet to V3 passes PRE migrate checks ✅",);
+
+        Ok(())
+    }
+
+    fn on_runtime_upgrade() -> Weight {
+        if PalletVersion::<T>::get() >= types::StorageVersion::V3 {
+            info!(" >>> AddWithdrawTransactionSource already applied, skipping");
+            return T::DbWeight::get().reads(1);
+        }
+
+        // The lazy V1 -> V2 rename must fully drain before this migration
+        // bumps the version to V3: otherwise this `translate` would run
+        // against a `WithdrawTransactions` map that the rename is still
+        // populating with pre-`source` bytes, and every entry the rename
+        // moves in afterwards would be stuck below the V3 shape other
+        // code now assumes.
+        if MigrationInProgress::<T>::get() {
+            info!(" >>> RenameBurnToWithdraw still draining, deferring AddWithdrawTransactionSource");
+            return T::DbWeight::get().reads(1);
+        }
+
+        add_withdraw_transaction_source::<T>()
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn post_upgrade() -> Result<(), &'static str> {
+        assert!(PalletVersion::<T>::get() >= types::StorageVersion::V3);
+
+        let pre_tx_count = Self::get_temp_storage("pre_withdraw_tx_count").unwrap_or(0u64);
+        let post_tx_count: u64 = WithdrawTransactions::<T>::iter_keys()
             .count()
             .saturated_into();
-        let post_tx_id = Pallet::<T>::withdraw_transaction_id();
-        let post_tx_fee = Pallet::<T>::withdraw_fee();
 
-        // Display post migration state
-        log::info!("🔎 RenameBurnToWithdraw post migration:");
-        log::info!(
-            " <-- burn tx count: {:?}",
-            BurnTransactions::<T>::iter_keys().count()
-        );
-        log::info!(
-            " <-- executed burn tx count: {:?}",
-            ExecutedBurnTransactions::<T>::iter_keys().count()
-        );
-        log::info!(" <-- burn tx id: {:?}", Pallet::<T>::burn_transaction_id());
-        log::info!(" <-- burn fee: {:?}", Pallet::<T>::burn_fee());
+        log::info!("🔎 AddWithdrawTransactionSource post migration:");
         log::info!(" --> withdraw tx count: {:?}", post_tx_count);
-        log::info!(
-            " --> executed withdraw tx count: {:?}",
-            post_executed_tx_count
-        );
-        log::info!(" --> withdraw tx id: {:?}", post_tx_id);
-        log::info!(" --> withdraw fee: {:?}", post_tx_fee);
 
-        // Check transactions against pre-check result
         assert_eq!(
             pre_tx_count, post_tx_count,
-            "Number of transactions migrated does not match"
+            "Number of withdraw transactions migrated does not match"
         );
-        assert_eq!(
-            pre_executed_tx_count, post_executed_tx_count,
-            "Number of executed transactions migrated does not match"
+
+        info!(
+            "👥  TFChain TFT Bridge pallet migration to {:?} passes POST migrate checks ✅",
+            Pallet::<T>::pallet_version()
         );
-        assert_eq!(
-            pre_tx_id, post_tx_id,
-            "Number of executed transactions migrated does not match"
+
+        Ok(())
+    }
+}
+
+/// Shape of `WithdrawTransaction` as it was encoded before this
+/// migration, used only to decode existing values.
+#[derive(codec::Decode)]
+struct WithdrawTransactionV2<AccountId> {
+    block: u64,
+    amount: u64,
+    target: sp_std::vec::Vec<u8>,
+    signatures: sp_std::vec::Vec<AccountId>,
+    sequence_number: u64,
+}
+
+/// Re-encodes every existing `WithdrawTransaction` with `source: None`,
+/// since historical withdraws were created before the field existed.
+pub fn add_withdraw_transaction_source<T: Config>() -> Weight {
+    info!(" >>> Adding source field to withdraw transactions...");
+    let mut reads_writes = 0u64;
+
+    WithdrawTransactions::<T>::translate::<WithdrawTransactionV2<T::AccountId>, _>(|_id, old| {
+        reads_writes += 1;
+        Some(types::WithdrawTransaction {
+            block: old.block,
+            amount: old.amount,
+            source: None,
+            target: old.target,
+            signatures: old.signatures,
+            sequence_number: old.sequence_number,
+        })
+    });
+
+    PalletVersion::<T>::set(types::StorageVersion::V3);
+    reads_writes += 1;
+    info!(" <<< Storage version upgraded to V3");
+
+    T::DbWeight::get().reads_writes(reads_writes, reads_writes)
+}
+
+pub struct AddExecutedWithdrawFee<T: Config>(PhantomData<T>);
+
+impl<T: Config> OnRuntimeUpgrade for AddExecutedWithdrawFee<T> {
+    #[cfg(feature = "try-runtime")]
+    fn pre_upgrade() -> Result<(), &'static str> {
+        assert!(
+            PalletVersion::<T>::get() < types::StorageVersion::V4,
+            "AddExecutedWithdrawFee must only run below storage version V4"
         );
+
+        let tx_count: u64 = ExecutedWithdrawTransactions::<T>::iter_keys()
+            .count()
+            .saturated_into();
+        Self::set_temp_storage(tx_count, "pre_executed_withdraw_tx_count");
+
+        log::info!("🔎 AddExecutedWithdrawFee pre migration:");
+        log::info!(" --> executed withdraw tx count: {:?}", tx_count);
+        log::info!("👥  TFChain TFT Bridge pallet to V4 passes PRE migrate checks ✅",);
+
+        Ok(())
+    }
+
+    fn on_runtime_upgrade() -> Weight {
+        if PalletVersion::<T>::get() >= types::StorageVersion::V4 {
+            info!(" >>> AddExecutedWithdrawFee already applied, skipping");
+            return T::DbWeight::get().reads(1);
+        }
+
+        // Don't let this migration race the still-draining lazy rename:
+        // entries the rename moves in afterwards carry a default `fee`
+        // rather than the `WithdrawFee` this migration fills in.
+        if MigrationInProgress::<T>::get() {
+            info!(" >>> RenameBurnToWithdraw still draining, deferring AddExecutedWithdrawFee");
+            return T::DbWeight::get().reads(1);
+        }
+
+        add_executed_withdraw_fee::<T>()
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn post_upgrade() -> Result<(), &'static str> {
+        assert!(PalletVersion::<T>::get() >= types::StorageVersion::V4);
+
+        let pre_tx_count = Self::get_temp_storage("pre_executed_withdraw_tx_count").unwrap_or(0u64);
+        let post_tx_count: u64 = ExecutedWithdrawTransactions::<T>::iter_keys()
+            .count()
+            .saturated_into();
+
+        log::info!("🔎 AddExecutedWithdrawFee post migration:");
+        log::info!(" --> executed withdraw tx count: {:?}", post_tx_count);
+
         assert_eq!(
-            pre_tx_fee, post_tx_fee,
-            "Number of executed transactions migrated does not match"
+            pre_tx_count, post_tx_count,
+            "Number of executed withdraw transactions migrated does not match"
         );
 
         info!(
@@ -123,43 +431,45 @@ impl<T: Config> OnRuntimeUpgrade for RenameBurnToWithdraw<T> {
     }
 }
 
-pub fn rename_burn_to_withdraw<T: Config>() -> frame_support::weights::Weight {
-    info!(" >>> Migrating transactions storage...");
-    let mut reads = 0;
-    let mut writes = 0;
+/// Shape of `ExecutedWithdrawTransaction` as it was encoded before this
+/// migration, used only to decode existing values.
+#[derive(codec::Decode)]
+struct ExecutedWithdrawTransactionV3<AccountId> {
+    block: u64,
+    amount: u64,
+    source: Option<sp_std::vec::Vec<u8>>,
+    target: sp_std::vec::Vec<u8>,
+    tx_id: u64,
+    signatures: sp_std::vec::Vec<AccountId>,
+}
 
-    // Move burn tx storage to withdraw tx storage
-    move_prefix(
-        &storage_prefix(b"TFTBridgeModule", b"BurnTransactions"),
-        &storage_prefix(b"TFTBridgeModule", b"WithdrawTransactions"),
-    );
-    reads += BurnTransactions::<T>::iter_keys().count();
-    writes += WithdrawTransactions::<T>::iter_keys().count();
+/// Populates the new `fee` field on every existing
+/// `ExecutedWithdrawTransaction` from the current `WithdrawFee` value,
+/// since that was the only fee schedule in force before this field
+/// started being recorded per-transaction.
+pub fn add_executed_withdraw_fee<T: Config>() -> Weight {
+    info!(" >>> Adding fee field to executed withdraw transactions...");
+    let mut reads_writes = 0u64;
+    let current_fee = Pallet::<T>::withdraw_fee();
 
-    // Move executed burn tx storage to executed withdraw tx storage
-    move_prefix(
-        &storage_prefix(b"TFTBridgeModule", b"ExecutedBurnTransactions"),
-        &storage_prefix(b"TFTBridgeModule", b"ExecutedWithdrawTransactions"),
+    ExecutedWithdrawTransactions::<T>::translate::<ExecutedWithdrawTransactionV3<T::AccountId>, _>(
+        |_id, old| {
+            reads_writes += 1;
+            Some(types::ExecutedWithdrawTransaction {
+                block: old.block,
+                amount: old.amount,
+                source: old.source,
+                target: old.target,
+                tx_id: old.tx_id,
+                signatures: old.signatures,
+                fee: current_fee,
+            })
+        },
     );
-    reads += ExecutedBurnTransactions::<T>::iter_keys().count();
-    writes += ExecutedWithdrawTransactions::<T>::iter_keys().count();
 
-    // Copy withdraw values from burn values
-    WithdrawTransactionID::<T>::set(Pallet::<T>::burn_transaction_id());
-    WithdrawFee::<T>::set(Pallet::<T>::burn_fee());
-    reads += 2;
-    writes += 2;
+    PalletVersion::<T>::set(types::StorageVersion::V4);
+    reads_writes += 1;
+    info!(" <<< Storage version upgraded to V4");
 
-    // Reset burn values
-    BurnTransactionID::<T>::set(0);
-    BurnFee::<T>::set(0);
-    writes += 2;
-
-    // Update pallet storage version
-    PalletVersion::<T>::set(types::StorageVersion::V2);
-    writes += 1;
-    info!(" <<< Storage version upgraded");
-
-    // Return the weight consumed by the migration.
-    T::DbWeight::get().reads_writes(reads as Weight, writes as Weight)
-}
\ No newline at end of file
+    T::DbWeight::get().reads_writes(reads_writes, reads_writes)
+}
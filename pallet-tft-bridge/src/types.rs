@@ -0,0 +1,89 @@
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_std::prelude::*;
+
+/// On-chain storage version of the TFT bridge pallet, used to gate
+/// migrations so that `on_runtime_upgrade` stays idempotent.
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, TypeInfo)]
+pub enum StorageVersion {
+    V1,
+    V2,
+    V3,
+    V4,
+}
+
+impl Default for StorageVersion {
+    fn default() -> Self {
+        StorageVersion::V1
+    }
+}
+
+/// Which of the two burn storage maps the lazy rename migration is
+/// currently draining.
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, TypeInfo)]
+pub enum MigrationStage {
+    BurnTransactions,
+    ExecutedBurnTransactions,
+}
+
+impl Default for MigrationStage {
+    fn default() -> Self {
+        MigrationStage::BurnTransactions
+    }
+}
+
+/// Progress marker for the lazy (multi-block) burn -> withdraw rename
+/// migration. `cursor` is the last raw storage key processed so far
+/// within `stage`; `None` means `stage` has not been started yet.
+#[derive(Encode, Decode, Default, Debug, Clone, PartialEq, Eq, TypeInfo)]
+pub struct MigrationState {
+    pub stage: MigrationStage,
+    pub cursor: Option<Vec<u8>>,
+}
+
+#[derive(Encode, Decode, Default, Debug, Clone, PartialEq, Eq, TypeInfo)]
+pub struct BurnTransaction<AccountId> {
+    pub block: u64,
+    pub amount: u64,
+    pub target: Vec<u8>,
+    pub signatures: Vec<AccountId>,
+    pub sequence_number: u64,
+}
+
+#[derive(Encode, Decode, Default, Debug, Clone, PartialEq, Eq, TypeInfo)]
+pub struct WithdrawTransaction<AccountId> {
+    pub block: u64,
+    pub amount: u64,
+    /// Originating Stellar/account address the withdraw was requested
+    /// from, so the bridge daemon can attribute or refund an invalid
+    /// target. `None` for transactions created before this field existed.
+    pub source: Option<Vec<u8>>,
+    pub target: Vec<u8>,
+    pub signatures: Vec<AccountId>,
+    pub sequence_number: u64,
+}
+
+#[derive(Encode, Decode, Default, Debug, Clone, PartialEq, Eq, TypeInfo)]
+pub struct ExecutedWithdrawTransaction<AccountId, Balance> {
+    pub block: u64,
+    pub amount: u64,
+    pub source: Option<Vec<u8>>,
+    pub target: Vec<u8>,
+    pub tx_id: u64,
+    pub signatures: Vec<AccountId>,
+    /// Fee actually deducted at execution time, so reconciliation against
+    /// Stellar doesn't have to re-derive the fee schedule in force then.
+    pub fee: Balance,
+}
+
+/// A withdraw transaction whose target the bridge could not deliver to,
+/// refunded back to its `source` account instead of being executed.
+#[derive(Encode, Decode, Default, Debug, Clone, PartialEq, Eq, TypeInfo)]
+pub struct RefundTransaction<AccountId> {
+    pub block: u64,
+    pub amount: u64,
+    pub source: Option<Vec<u8>>,
+    pub target: Vec<u8>,
+    pub tx_id: u64,
+    pub signatures: Vec<AccountId>,
+}